@@ -1,60 +1,229 @@
 use crate::frame::{Frame, FrameHeader};
 
-use amqp_serde::{constants::FRAME_END, to_buffer, types::ShortUint};
+use amqp_serde::{
+    constants::{FRAME_BODY, FRAME_END, FRAME_HEARTBEAT},
+    to_buffer,
+    types::{LongUint, ShortUint},
+};
 use bytes::{Buf, BytesMut};
 use serde::Serialize;
-use std::io;
+use std::{fmt, io, sync::Arc, time::Duration};
 use tokio::{
-    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    io::{duplex, split, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadHalf, WriteHalf},
     net::{
         tcp::{OwnedReadHalf, OwnedWriteHalf},
         TcpStream,
     },
+    time,
 };
+use tokio_rustls::{client::TlsStream, rustls, TlsConnector};
 
 const DEFAULT_BUFFER_SIZE: usize = 8192;
 
+/// Upper bound on a single content delivery's body size. `content_header.body_size`
+/// is peer-controlled, so it's validated against this before being trusted for
+/// any allocation; a legitimately larger payload should be chunked by the
+/// application layer rather than raising this limit.
+const MAX_CONTENT_BODY_SIZE: usize = 128 * 1024 * 1024;
+
+/// Errors that can occur while reading/writing frames on a connection.
+///
+/// `CorruptedFrame`, `PeerShutdown`, `ConnectionReset` and `ProtocolViolation`
+/// are all fatal: the connection can no longer make progress and the caller
+/// must tear it down rather than retry the failing operation. `Io` covers
+/// whatever's left after `From<io::Error>` has already peeled off the
+/// well-known dead-connection kinds (reset, broken pipe, aborted, ...), so in
+/// practice it's the transient/unexpected leftovers. Use
+/// [`ConnectionError::is_fatal`] to tell fatal errors apart from the rest.
+#[derive(Debug)]
+pub enum ConnectionError {
+    /// Peer closed the connection cleanly (read returned 0 with an empty buffer).
+    PeerShutdown,
+    /// Peer closed the connection with unconsumed bytes still in the buffer,
+    /// or the transport reported a reset, broken pipe, abort, not-connected
+    /// or unexpected-EOF I/O error.
+    ConnectionReset,
+    /// A frame failed to decode; the stream can no longer be trusted.
+    CorruptedFrame,
+    /// Peer sent something that violates the protocol (e.g. an unexpected frame).
+    ProtocolViolation(String),
+    /// No bytes arrived within the heartbeat timeout, or the transport
+    /// reported a timed-out I/O error.
+    HeartbeatTimeout,
+    /// Failed to serialize an outgoing value/frame. This is a local encoding
+    /// bug, not the peer's fault, so unlike `ProtocolViolation` it is not
+    /// fatal: the connection is still usable, the failing write just didn't
+    /// go out.
+    EncodeFailed(String),
+    /// Any other I/O failure.
+    Io(io::Error),
+}
+
+impl ConnectionError {
+    /// Whether the connection must be closed, as opposed to the operation
+    /// simply being retried.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            ConnectionError::PeerShutdown
+                | ConnectionError::ConnectionReset
+                | ConnectionError::CorruptedFrame
+                | ConnectionError::ProtocolViolation(_)
+        )
+    }
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionError::PeerShutdown => write!(f, "peer shutdown"),
+            ConnectionError::ConnectionReset => write!(f, "connection reset"),
+            ConnectionError::CorruptedFrame => write!(f, "corrupted frame"),
+            ConnectionError::ProtocolViolation(msg) => write!(f, "protocol violation: {msg}"),
+            ConnectionError::HeartbeatTimeout => write!(f, "heartbeat timeout"),
+            ConnectionError::EncodeFailed(msg) => write!(f, "failed to encode outgoing frame: {msg}"),
+            ConnectionError::Io(err) => write!(f, "I/O error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectionError {}
+
+impl From<io::Error> for ConnectionError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::TimedOut => ConnectionError::HeartbeatTimeout,
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof => ConnectionError::ConnectionReset,
+            _ => ConnectionError::Io(err),
+        }
+    }
+}
+
+/// Splits an owned transport into its read/write halves. Implemented for
+/// `TcpStream` (using its zero-cost owned halves) and for `TlsStream<TcpStream>`
+/// (using `tokio::io::split`), so `SplitConnection` can hand back a connected
+/// `Reader`/`Writer` pair regardless of which transport carries the connection.
+pub trait IntoSplit {
+    type ReadHalf: AsyncRead + Unpin + Send;
+    type WriteHalf: AsyncWrite + Unpin + Send;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf);
+}
+
+impl IntoSplit for TcpStream {
+    type ReadHalf = OwnedReadHalf;
+    type WriteHalf = OwnedWriteHalf;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        TcpStream::into_split(self)
+    }
+}
+
+impl IntoSplit for TlsStream<TcpStream> {
+    type ReadHalf = ReadHalf<TlsStream<TcpStream>>;
+    type WriteHalf = WriteHalf<TlsStream<TcpStream>>;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        split(self)
+    }
+}
+
+impl IntoSplit for DuplexStream {
+    type ReadHalf = ReadHalf<DuplexStream>;
+    type WriteHalf = WriteHalf<DuplexStream>;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        split(self)
+    }
+}
+
 pub struct SplitConnection;
-pub struct Reader {
-    stream: OwnedReadHalf,
+pub struct Reader<R> {
+    stream: R,
     buffer: BytesMut,
 }
-pub struct Writer {
-    stream: OwnedWriteHalf,
+pub struct Writer<W> {
+    stream: W,
     buffer: BytesMut,
 }
 
 impl SplitConnection {
-    pub async fn open(addr: &str) -> io::Result<(Reader, Writer)> {
+    pub async fn open(
+        addr: &str,
+    ) -> Result<(Reader<OwnedReadHalf>, Writer<OwnedWriteHalf>), ConnectionError> {
         let stream = TcpStream::connect(addr).await?;
-        let (reader, writer) = stream.into_split();
+        Ok(Self::from_stream(stream))
+    }
 
-        let read_buffer = BytesMut::with_capacity(DEFAULT_BUFFER_SIZE);
-        let write_buffer = BytesMut::with_capacity(DEFAULT_BUFFER_SIZE);
+    /// Connect over TLS (`amqps://`, typically port 5671). `server_name` is
+    /// the SNI name to present, `client_config` the rustls configuration to
+    /// validate the broker's certificate against.
+    pub async fn open_tls(
+        addr: &str,
+        server_name: rustls::ServerName,
+        client_config: Arc<rustls::ClientConfig>,
+    ) -> Result<
+        (
+            Reader<ReadHalf<TlsStream<TcpStream>>>,
+            Writer<WriteHalf<TlsStream<TcpStream>>>,
+        ),
+        ConnectionError,
+    > {
+        let stream = TcpStream::connect(addr).await?;
+        let stream = TlsConnector::from(client_config)
+            .connect(server_name, stream)
+            .await?;
+        Ok(Self::from_stream(stream))
+    }
+
+    /// Create a pair of in-memory, duplex-connected (Reader, Writer)
+    /// endpoints: one playing the client side, one playing the server side.
+    /// This lets protocol/framing logic be driven end-to-end against a
+    /// scripted fake peer in a plain `#[tokio::test]`, without a live broker.
+    pub fn pair(
+        buffer_size: usize,
+    ) -> (
+        (Reader<ReadHalf<DuplexStream>>, Writer<WriteHalf<DuplexStream>>),
+        (Reader<ReadHalf<DuplexStream>>, Writer<WriteHalf<DuplexStream>>),
+    ) {
+        let (client, server) = duplex(buffer_size);
+        (Self::from_stream(client), Self::from_stream(server))
+    }
+
+    fn from_stream<S: IntoSplit>(stream: S) -> (Reader<S::ReadHalf>, Writer<S::WriteHalf>) {
+        let (reader, writer) = stream.into_split();
 
-        Ok((
+        (
             Reader {
                 stream: reader,
-                buffer: read_buffer,
+                buffer: BytesMut::with_capacity(DEFAULT_BUFFER_SIZE),
             },
             Writer {
                 stream: writer,
-                buffer: write_buffer,
+                buffer: BytesMut::with_capacity(DEFAULT_BUFFER_SIZE),
             },
-        ))
+        )
     }
 }
-impl Writer {
-    pub async fn write<T: Serialize>(&mut self, value: &T) -> io::Result<usize> {
+impl<W: AsyncWrite + Unpin> Writer<W> {
+    pub async fn write<T: Serialize>(&mut self, value: &T) -> Result<usize, ConnectionError> {
         to_buffer(value, &mut self.buffer)
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            .map_err(|err| ConnectionError::EncodeFailed(err.to_string()))?;
         let len = self.buffer.len();
         self.stream.write_all(&self.buffer).await?;
         self.buffer.advance(len);
         Ok(len)
     }
 
-    pub async fn write_frame(&mut self, channel: ShortUint, frame: Frame) -> io::Result<usize> {
+    pub async fn write_frame(
+        &mut self,
+        channel: ShortUint,
+        frame: Frame,
+    ) -> Result<usize, ConnectionError> {
         // reserve bytes for frame header, which to be updated after encoding payload
         let header = FrameHeader {
             frame_type: frame.get_frame_type(),
@@ -65,7 +234,7 @@ impl Writer {
 
         // encode payload
         let payload_size = to_buffer(&frame, &mut self.buffer)
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            .map_err(|err| ConnectionError::EncodeFailed(err.to_string()))?;
 
         // update frame's payload size
         for (i, v) in (payload_size as u32).to_be_bytes().iter().enumerate() {
@@ -86,27 +255,109 @@ impl Writer {
         Ok(len)
     }
 
-    pub async fn close(&mut self) -> io::Result<()> {
+    /// Write a content header frame followed by as many body frames as it
+    /// takes to carry `body` (none for an empty body), chopping `body` so
+    /// that no body frame's payload exceeds `frame_max - 8` bytes, as
+    /// required by the connection's negotiated `TuneOk::frame_max`. Per
+    /// AMQP 0-9-1, `frame_max == 0` means the peer imposes no limit, so a
+    /// non-empty body is written as a single chunk.
+    pub async fn write_content(
+        &mut self,
+        channel: ShortUint,
+        header_frame: Frame,
+        body: &[u8],
+        frame_max: LongUint,
+    ) -> Result<usize, ConnectionError> {
+        let mut total = self.write_frame(channel, header_frame).await?;
+
+        if frame_max == 0 {
+            if !body.is_empty() {
+                total += self.write_body_chunk(channel, body).await?;
+            }
+            return Ok(total);
+        }
+
+        let max_chunk_size = (frame_max as usize).saturating_sub(8).max(1);
+        for chunk in body.chunks(max_chunk_size) {
+            total += self.write_body_chunk(channel, chunk).await?;
+        }
+
+        Ok(total)
+    }
+
+    async fn write_body_chunk(
+        &mut self,
+        channel: ShortUint,
+        chunk: &[u8],
+    ) -> Result<usize, ConnectionError> {
+        let header = FrameHeader {
+            frame_type: FRAME_BODY,
+            channel,
+            payload_size: chunk.len() as u32,
+        };
+        to_buffer(&header, &mut self.buffer).unwrap();
+        self.buffer.extend_from_slice(chunk);
+        to_buffer(&FRAME_END, &mut self.buffer).unwrap();
+
+        self.stream.write_all(&self.buffer).await?;
+        let len = self.buffer.len();
+        self.buffer.advance(len);
+        Ok(len)
+    }
+
+    /// Emit a single type-8 heartbeat frame (channel 0, empty payload). The
+    /// peer expects one every `heartbeat` seconds, the interval negotiated in
+    /// `TuneOk`; this method does not schedule any further heartbeats.
+    pub async fn write_heartbeat(&mut self) -> Result<usize, ConnectionError> {
+        let header = FrameHeader {
+            frame_type: FRAME_HEARTBEAT,
+            channel: 0,
+            payload_size: 0,
+        };
+        to_buffer(&header, &mut self.buffer).unwrap();
+        to_buffer(&FRAME_END, &mut self.buffer).unwrap();
+
+        self.stream.write_all(&self.buffer).await?;
+        let len = self.buffer.len();
+        self.buffer.advance(len);
+        Ok(len)
+    }
+
+    pub async fn close(&mut self) -> Result<(), ConnectionError> {
         // TODO: flush buffers if is not empty?
-        self.stream.shutdown().await
+        self.stream.shutdown().await?;
+        Ok(())
     }
 }
-impl Reader {
+impl<R: AsyncRead + Unpin> Reader<R> {
     /// To support channels multiplex on one connection
     /// we need to return the channel id.
     /// Return :
     ///     (channel_id, Frame)
-    pub async fn read_frame(&mut self) -> io::Result<(ShortUint, Frame)> {
-        // TODO: handle network error, such as timeout, corrupted frame
+    pub async fn read_frame(&mut self) -> Result<(ShortUint, Frame), ConnectionError> {
+        self.read_frame_with_timeout(None).await
+    }
+
+    /// Like [`Reader::read_frame`], but fails with [`ConnectionError::HeartbeatTimeout`]
+    /// if no bytes arrive within `timeout`. Callers derive `timeout` as
+    /// roughly twice the negotiated heartbeat interval, so a silently dead
+    /// peer is detected instead of blocking forever.
+    pub async fn read_frame_with_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(ShortUint, Frame), ConnectionError> {
         loop {
-            let len = self.stream.read_buf(&mut self.buffer).await?;
+            let len = match timeout {
+                Some(d) => time::timeout(d, self.stream.read_buf(&mut self.buffer))
+                    .await
+                    .map_err(|_| ConnectionError::HeartbeatTimeout)??,
+                None => self.stream.read_buf(&mut self.buffer).await?,
+            };
             if len == 0 {
                 if self.buffer.is_empty() {
-                    //TODO: map to own error
-                    return Err(io::Error::new(io::ErrorKind::Other, "peer shutdown"));
+                    return Err(ConnectionError::PeerShutdown);
                 } else {
-                    //TODO: map to own error
-                    return Err(io::Error::new(io::ErrorKind::Other, "connection failure"));
+                    return Err(ConnectionError::ConnectionReset);
                 }
             }
             // TODO: replace with tracing
@@ -123,17 +374,241 @@ impl Reader {
                 Err(err) => match err {
                     crate::frame::Error::Incomplete => continue,
                     crate::frame::Error::Corrupted => {
-                        // TODO: map this error to indicate connection to be shutdown
-                        return Err(io::Error::new(
-                            io::ErrorKind::Other,
-                            "corrupted frame, should close the connection",
-                        ));
+                        // Stream position is no longer trustworthy; the connection must be closed.
+                        return Err(ConnectionError::CorruptedFrame);
+                    }
+                    crate::frame::Error::Other(msg) => {
+                        return Err(ConnectionError::ProtocolViolation(format!("{msg:?}")))
                     }
-                    crate::frame::Error::Other(_) => todo!(),
                 },
             }
         }
     }
+
+    /// Read one logical content delivery: a content header frame followed by
+    /// as many body frames as it takes to collect `body_size` bytes, mirroring
+    /// a chunked streaming-body reassembly on top of the low-level
+    /// [`Reader::read_frame`].
+    pub async fn read_content(&mut self) -> Result<(ShortUint, Frame, Vec<u8>), ConnectionError> {
+        let (channel, header) = self.read_frame().await?;
+
+        let body_size = match &header {
+            Frame::Header(_, content_header) => content_header.body_size as usize,
+            _ => {
+                return Err(ConnectionError::ProtocolViolation(
+                    "expected content header frame".to_string(),
+                ))
+            }
+        };
+
+        if body_size > MAX_CONTENT_BODY_SIZE {
+            return Err(ConnectionError::ProtocolViolation(format!(
+                "content body size {body_size} exceeds the {MAX_CONTENT_BODY_SIZE}-byte limit"
+            )));
+        }
+
+        // Bound the upfront allocation instead of trusting the peer-controlled
+        // `body_size` outright; the `Vec` grows incrementally as body frames
+        // arrive, up to the validated cap above.
+        let mut body = Vec::with_capacity(body_size.min(DEFAULT_BUFFER_SIZE));
+        while body.len() < body_size {
+            let (frame_channel, frame) = self.read_frame().await?;
+            match frame {
+                Frame::Body(_, chunk) if frame_channel == channel => {
+                    body.extend_from_slice(&chunk)
+                }
+                Frame::Body(..) => {
+                    return Err(ConnectionError::ProtocolViolation(format!(
+                        "body frame for channel {frame_channel} interleaved with delivery on channel {channel}"
+                    )))
+                }
+                _ => {
+                    return Err(ConnectionError::ProtocolViolation(
+                        "expected content body frame".to_string(),
+                    ))
+                }
+            }
+        }
+
+        Ok((channel, header, body))
+    }
+}
+
+/// Retry policy used by [`ReconnectingConnection`] when a reconnect attempt
+/// fails: how many times to retry and how long to back off between attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Wraps a plain-TCP [`SplitConnection`] and transparently reconnects when a
+/// fatal [`ConnectionError`] is observed on read: it re-opens the socket,
+/// replays the protocol header and Start/StartOk/Tune/TuneOk/Open handshake,
+/// reopens any channels the caller told it about, and otherwise preserves the
+/// `(channel_id, Frame)` read interface so existing consumers are unaffected
+/// when reconnection succeeds. A heartbeat timeout also triggers a reconnect,
+/// as the peer is presumed dead; other non-fatal errors (e.g. `Io`) are
+/// surfaced to the caller without triggering a reconnect.
+pub struct ReconnectingConnection {
+    addr: String,
+    policy: RetryPolicy,
+    heartbeat_timeout: Option<Duration>,
+    reader: Reader<OwnedReadHalf>,
+    writer: Writer<OwnedWriteHalf>,
+    open_channels: Vec<ShortUint>,
+}
+
+impl ReconnectingConnection {
+    pub async fn open(addr: &str, policy: RetryPolicy) -> Result<Self, ConnectionError> {
+        let (reader, writer) = SplitConnection::open(addr).await?;
+        let mut conn = Self {
+            addr: addr.to_string(),
+            policy,
+            heartbeat_timeout: None,
+            reader,
+            writer,
+            open_channels: Vec::new(),
+        };
+        conn.handshake().await?;
+        Ok(conn)
+    }
+
+    /// Fail reads with [`ConnectionError::HeartbeatTimeout`] — which
+    /// triggers a reconnect just like any other fatal error — if no bytes
+    /// arrive within `timeout`. Callers derive `timeout` as roughly twice
+    /// the negotiated heartbeat interval, per [`Reader::read_frame_with_timeout`].
+    pub fn set_heartbeat_timeout(&mut self, timeout: Option<Duration>) {
+        self.heartbeat_timeout = timeout;
+    }
+
+    /// Replay the protocol header and the Start/StartOk/Tune/TuneOk/Open
+    /// handshake against whatever transport is currently installed.
+    async fn handshake(&mut self) -> Result<(), ConnectionError> {
+        self.writer.write(&crate::frame::ProtocolHeader::default()).await?;
+
+        let (_, start) = self.reader.read_frame().await?;
+        if !matches!(start, Frame::Start(..)) {
+            return Err(ConnectionError::ProtocolViolation(
+                "expected Start".to_string(),
+            ));
+        }
+
+        self.writer
+            .write_frame(0, crate::frame::StartOk::default().into_frame())
+            .await?;
+
+        let (_, tune) = self.reader.read_frame().await?;
+        let tune = match tune {
+            Frame::Tune(_, v) => v,
+            _ => return Err(ConnectionError::ProtocolViolation("expected Tune".to_string())),
+        };
+
+        let mut tune_ok = crate::frame::TuneOk::default();
+        tune_ok.channel_max = tune.channel_max;
+        tune_ok.frame_max = tune.frame_max;
+        tune_ok.heartbeat = tune.heartbeat;
+        self.writer.write_frame(0, tune_ok.into_frame()).await?;
+
+        self.writer
+            .write_frame(0, crate::frame::Open::default().into_frame())
+            .await?;
+
+        let (_, open_ok) = self.reader.read_frame().await?;
+        if !matches!(open_ok, Frame::OpenOk(..)) {
+            return Err(ConnectionError::ProtocolViolation(
+                "expected OpenOk".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Record that `channel` is open, so a future reconnect re-opens it too.
+    pub fn note_channel_open(&mut self, channel: ShortUint) {
+        self.open_channels.push(channel);
+    }
+
+    /// Stop tracking `channel` (e.g. once the caller closed it deliberately).
+    pub fn note_channel_close(&mut self, channel: ShortUint) {
+        self.open_channels.retain(|c| *c != channel);
+    }
+
+    pub async fn write_frame(
+        &mut self,
+        channel: ShortUint,
+        frame: Frame,
+    ) -> Result<usize, ConnectionError> {
+        self.writer.write_frame(channel, frame).await
+    }
+
+    /// Read the next frame, transparently reconnecting and replaying the
+    /// handshake if the current transport has failed fatally — a heartbeat
+    /// timeout counts as fatal here, since it means the peer is presumed dead.
+    pub async fn read_frame(&mut self) -> Result<(ShortUint, Frame), ConnectionError> {
+        match self.reader.read_frame_with_timeout(self.heartbeat_timeout).await {
+            Ok(frame) => Ok(frame),
+            Err(err) if err.is_fatal() || matches!(err, ConnectionError::HeartbeatTimeout) => {
+                self.reconnect().await?;
+                self.reader.read_frame_with_timeout(self.heartbeat_timeout).await
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn reconnect(&mut self) -> Result<(), ConnectionError> {
+        let mut backoff = self.policy.initial_backoff;
+        let mut last_err = None;
+
+        for _attempt in 0..self.policy.max_attempts {
+            match self.try_reconnect_once().await {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    last_err = Some(err);
+                    time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.policy.max_backoff);
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or(ConnectionError::ConnectionReset))
+    }
+
+    /// Open a fresh transport, replay the handshake and re-open the tracked
+    /// channels. Any failure here (including mid-handshake) just means this
+    /// attempt failed; the caller is responsible for retrying with backoff.
+    async fn try_reconnect_once(&mut self) -> Result<(), ConnectionError> {
+        let (reader, writer) = SplitConnection::open(&self.addr).await?;
+        self.reader = reader;
+        self.writer = writer;
+        self.handshake().await?;
+
+        for channel in self.open_channels.clone() {
+            self.writer
+                .write_frame(channel, crate::frame::ChannelOpen::default().into_frame())
+                .await?;
+
+            let (_, channel_open_ok) = self.reader.read_frame().await?;
+            if !matches!(channel_open_ok, Frame::ChannelOpenOk(..)) {
+                return Err(ConnectionError::ProtocolViolation(format!(
+                    "expected ChannelOpenOk for channel {channel}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -213,4 +688,202 @@ mod test {
             println!("{close_ok:?}");
         })
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_handshake_over_inmemory_transport() {
+        // Drives the same Start/StartOk/Tune/TuneOk/Open/Close handshake as
+        // `test_client_establish_connection`, but against a scripted fake
+        // server over an in-memory duplex pair instead of a live broker.
+        let rt = new_runtime();
+        rt.block_on(async {
+            let ((mut client_reader, mut client_writer), (mut server_reader, mut server_writer)) =
+                SplitConnection::pair(4096);
+
+            let server = tokio::spawn(async move {
+                let _protocol_header = server_reader.read_frame().await;
+
+                server_writer
+                    .write_frame(0, Start::default().into_frame())
+                    .await
+                    .unwrap();
+
+                let (_, _start_ok) = server_reader.read_frame().await.unwrap();
+
+                server_writer
+                    .write_frame(0, Tune::default().into_frame())
+                    .await
+                    .unwrap();
+
+                let (_, _tune_ok) = server_reader.read_frame().await.unwrap();
+                let (_, _open) = server_reader.read_frame().await.unwrap();
+
+                server_writer
+                    .write_frame(0, OpenOk::default().into_frame())
+                    .await
+                    .unwrap();
+
+                let (_, _close) = server_reader.read_frame().await.unwrap();
+
+                server_writer
+                    .write_frame(0, CloseOk::default().into_frame())
+                    .await
+                    .unwrap();
+            });
+
+            client_writer.write(&ProtocolHeader::default()).await.unwrap();
+
+            let (_, start) = client_reader.read_frame().await.unwrap();
+            assert!(matches!(start, Frame::Start(..)));
+
+            client_writer
+                .write_frame(0, StartOk::default().into_frame())
+                .await
+                .unwrap();
+
+            let (_, tune) = client_reader.read_frame().await.unwrap();
+            let tune = match tune {
+                Frame::Tune(_, v) => v,
+                _ => panic!("wrong message"),
+            };
+
+            let mut tune_ok = TuneOk::default();
+            tune_ok.channel_max = tune.channel_max;
+            tune_ok.frame_max = tune.frame_max;
+            tune_ok.heartbeat = tune.heartbeat;
+            client_writer.write_frame(0, tune_ok.into_frame()).await.unwrap();
+
+            client_writer.write_frame(0, Open::default().into_frame()).await.unwrap();
+
+            let (_, open_ok) = client_reader.read_frame().await.unwrap();
+            assert!(matches!(open_ok, Frame::OpenOk(..)));
+
+            client_writer
+                .write_frame(0, Close::default().into_frame())
+                .await
+                .unwrap();
+
+            let (_, close_ok) = client_reader.read_frame().await.unwrap();
+            assert!(matches!(close_ok, Frame::CloseOk(..)));
+
+            server.await.unwrap();
+        })
+    }
+
+    #[test]
+    fn test_write_heartbeat_and_read_with_timeout() {
+        use super::ConnectionError;
+        use std::time::Duration;
+
+        let rt = new_runtime();
+        rt.block_on(async {
+            let ((mut client_reader, _client_writer), (_server_reader, mut server_writer)) =
+                SplitConnection::pair(4096);
+
+            server_writer.write_heartbeat().await.unwrap();
+
+            let (channel, frame) = client_reader
+                .read_frame_with_timeout(Some(Duration::from_secs(1)))
+                .await
+                .unwrap();
+            assert_eq!(channel, 0);
+            assert!(matches!(frame, Frame::HeartBeat(..)));
+
+            // No more bytes are coming, so a short timeout should fail with
+            // `HeartbeatTimeout` rather than hanging forever.
+            let result = client_reader
+                .read_frame_with_timeout(Some(Duration::from_millis(50)))
+                .await;
+            assert!(matches!(result, Err(ConnectionError::HeartbeatTimeout)));
+        })
+    }
+
+    #[test]
+    fn test_reconnecting_connection_resyncs_after_fatal_error() {
+        use super::{ReconnectingConnection, RetryPolicy};
+        use std::time::Duration;
+
+        let rt = new_runtime();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+
+            let server = tokio::spawn(async move {
+                // First connection: complete the handshake, then die without
+                // warning so the client's next read observes a fatal error.
+                {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let (mut server_reader, mut server_writer) = SplitConnection::from_stream(stream);
+
+                    let _protocol_header = server_reader.read_frame().await;
+                    server_writer
+                        .write_frame(0, Start::default().into_frame())
+                        .await
+                        .unwrap();
+                    server_reader.read_frame().await.unwrap(); // StartOk
+                    server_writer
+                        .write_frame(0, Tune::default().into_frame())
+                        .await
+                        .unwrap();
+                    server_reader.read_frame().await.unwrap(); // TuneOk
+                    server_reader.read_frame().await.unwrap(); // Open
+                    server_writer
+                        .write_frame(0, OpenOk::default().into_frame())
+                        .await
+                        .unwrap();
+                }
+
+                // Second connection (the reconnect): replay the handshake,
+                // re-open the channel the client had tracked, then deliver a
+                // frame the client should read as if nothing happened.
+                let (stream, _) = listener.accept().await.unwrap();
+                let (mut server_reader, mut server_writer) = SplitConnection::from_stream(stream);
+
+                let _protocol_header = server_reader.read_frame().await;
+                server_writer
+                    .write_frame(0, Start::default().into_frame())
+                    .await
+                    .unwrap();
+                server_reader.read_frame().await.unwrap(); // StartOk
+                server_writer
+                    .write_frame(0, Tune::default().into_frame())
+                    .await
+                    .unwrap();
+                server_reader.read_frame().await.unwrap(); // TuneOk
+                server_reader.read_frame().await.unwrap(); // Open
+                server_writer
+                    .write_frame(0, OpenOk::default().into_frame())
+                    .await
+                    .unwrap();
+
+                let (_, channel_open) = server_reader.read_frame().await.unwrap();
+                assert!(matches!(channel_open, Frame::ChannelOpen(..)));
+                server_writer
+                    .write_frame(7, ChannelOpenOk::default().into_frame())
+                    .await
+                    .unwrap();
+
+                server_writer
+                    .write_frame(7, CloseOk::default().into_frame())
+                    .await
+                    .unwrap();
+            });
+
+            let policy = RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(50),
+            };
+            let mut conn = ReconnectingConnection::open(&addr, policy).await.unwrap();
+            conn.note_channel_open(7);
+
+            // First connection died after the handshake with no pending
+            // frame, so this read must trigger a reconnect-and-resync before
+            // it can succeed.
+            let (channel, frame) = conn.read_frame().await.unwrap();
+            assert_eq!(channel, 7);
+            assert!(matches!(frame, Frame::CloseOk(..)));
+
+            server.await.unwrap();
+        })
+    }
+}